@@ -3,8 +3,18 @@ use rust_decimal::prelude::*;
 use core::str::FromStr;
 use std::thread;
 use std::convert::TryInto;
-use std::collections::BinaryHeap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
 
+const LEHMER_A: i64 = 16807;
+const LEHMER_M: i64 = 2147483647;
+const LEHMER_Q: i64 = 127773;
+const LEHMER_R: i64 = 2836;
+
+// The generator's output lands in [0, LEHMER_M - 2] (see `schrage_next`), so
+// that's the full domain a bitset needs to cover.
+const TOTAL_OUTPUTS: u64 = (LEHMER_M - 1) as u64;
+const BITSET_WORDS: usize = (TOTAL_OUTPUTS as usize).div_ceil(64);
 
 fn pct_along(num: i64, den: i64, min_val: i64, max_val: i64) -> i64 {
     let numf = num as f64;
@@ -15,20 +25,561 @@ fn pct_along(num: i64, den: i64, min_val: i64, max_val: i64) -> i64 {
     return (numf / denf * (max_valf - min_valf) + min_valf) as i64;
 }
 
+/// Schrage's algorithm for `a*s mod m` using the precomputed `q = m/a` and
+/// `r = m%a`, so the multiplication never leaves i64 range. This is the exact
+/// integer equivalent of Park-Miller's minimal standard generator; the result
+/// stays in `[1, m)`.
+fn schrage_next(s: i64) -> i64 {
+    let hi = s / LEHMER_Q;
+    let lo = s % LEHMER_Q;
+    let mut s_next = LEHMER_A * lo - LEHMER_R * hi;
+    if s_next <= 0 {
+        s_next += LEHMER_M;
+    }
+    s_next
+}
+
+/// A deterministic SSN -> value map. Swapping the generator is how analysts
+/// compare which keyed hash gives the cleanest assignment, without touching
+/// the collision/uniformity/clustering machinery that consumes `output`.
+trait Generator {
+    fn name(&self) -> &'static str;
+    fn output(&self, ssn: i64) -> u64;
+
+    /// Exclusive upper bound of `output`'s range, i.e. `output` always lands
+    /// in `[0, output_domain)`. Anything that rescales a raw `output` into a
+    /// proportion (e.g. `assign_arm_with`'s alias-table coin flip) needs this
+    /// rather than assuming every generator shares Park-Miller's range.
+    fn output_domain(&self) -> f64;
+}
+
+/// The original Park-Miller minimal standard generator, keyed by treating
+/// the SSN itself as the Lehmer state.
+struct ParkMiller;
+
+impl Generator for ParkMiller {
+    fn name(&self) -> &'static str {
+        "park-miller"
+    }
+
+    fn output(&self, ssn: i64) -> u64 {
+        (schrage_next(ssn) - 1) as u64
+    }
+
+    fn output_domain(&self) -> f64 {
+        TOTAL_OUTPUTS as f64
+    }
+}
+
+/// A PCG32 (permuted congruential generator), seeded by mixing the SSN into
+/// its initial state and stepping once.
+struct Pcg32;
+
+impl Generator for Pcg32 {
+    fn name(&self) -> &'static str {
+        "pcg32"
+    }
+
+    fn output(&self, ssn: i64) -> u64 {
+        const MULT: u64 = 6364136223846793005;
+        const INC: u64 = 1442695040888963407;
+        let state = (ssn as u64).wrapping_mul(MULT).wrapping_add(INC);
+        let xorshifted = (((state >> 18) ^ state) >> 27) as u32;
+        let rot = (state >> 59) as u32;
+        xorshifted.rotate_right(rot) as u64
+    }
+
+    fn output_domain(&self) -> f64 {
+        // `output` is a u32 widened to u64, so it spans the full u32 range.
+        (u32::MAX as f64) + 1.0
+    }
+}
+
+/// A keyed stream hash in the spirit of SeaHash/ChaCha's diffusion rounds:
+/// the SSN is the only input, and a few rounds of multiply-xorshift (the
+/// "MurmurHash3 finalizer" mix) scramble it across all 64 bits, rather than
+/// Park-Miller's linear recurrence.
+struct SeaStream;
+
+impl Generator for SeaStream {
+    fn name(&self) -> &'static str {
+        "seastream"
+    }
+
+    fn output(&self, ssn: i64) -> u64 {
+        let mut x = ssn as u64;
+        x ^= x >> 33;
+        x = x.wrapping_mul(0xff51afd7ed558ccd);
+        x ^= x >> 33;
+        x = x.wrapping_mul(0xc4ceb9fe1a85ec53);
+        x ^= x >> 33;
+        x
+    }
+
+    fn output_domain(&self) -> f64 {
+        // `output` uses all 64 bits, so it spans the full u64 range.
+        (u64::MAX as f64) + 1.0
+    }
+}
+
+fn all_generators() -> Vec<Box<dyn Generator>> {
+    vec![Box::new(ParkMiller), Box::new(Pcg32), Box::new(SeaStream)]
+}
+
+/// The original `rust_decimal`-based step, kept only behind `--decimal` for
+/// cross-checking the integer fast path. Multi-hour on a full run, so it's
+/// not the default anymore.
+fn decimal_next(i: i64, ca: Decimal, cq: Decimal, cr: Decimal, cm: Decimal, zero: Decimal, one: Decimal) -> u64 {
+    let mut lsd = Decimal::from_i64(i).unwrap();
+    lsd.rescale(10);
+
+    let mut whi = lsd / cq;
+    whi = whi.round_dp_with_strategy(0, RoundingStrategy::ToZero);
+    whi.rescale(10);
+    let mut wlo = lsd - cq * whi;
+    wlo.rescale(10);
+    lsd = ca * wlo - cr * whi;
+
+    if lsd <= zero {
+        lsd = lsd + cm;
+    }
+    // `lsd` is now the exact integer state in [1, m), the same value
+    // `schrage_next` computes; shift it to [0, m - 2] like
+    // `ParkMiller::output` so it indexes the same bitset.
+    assert!(lsd >= one && lsd < cm);
+    (lsd.to_i64().unwrap() - 1) as u64
+}
+
+/// Command-line knobs. There's no argument-parsing crate pulled in for a
+/// one-off analysis tool, so this is just a small hand-rolled pass over
+/// `env::args()`.
+struct Args {
+    use_decimal: bool,
+    bit_shift: u32,
+    bit_width: u32,
+    bins: usize,
+    load_factor: f64,
+    arms: Option<Vec<f64>>,
+    compare: Option<(i64, i64)>,
+    mask: Option<String>,
+    generator: String,
+}
+
+fn parse_args() -> Args {
+    let argv: Vec<String> = std::env::args().collect();
+    let mut use_decimal = false;
+    let mut bit_shift: u32 = 0;
+    let mut bit_width: u32 = 20;
+    let mut bins: usize = 64;
+    let mut load_factor: f64 = 0.5;
+    let mut arms: Option<Vec<f64>> = None;
+    let mut compare: Option<(i64, i64)> = None;
+    let mut mask: Option<String> = None;
+    let mut generator = "park-miller".to_string();
+
+    let mut i = 1;
+    while i < argv.len() {
+        match argv[i].as_str() {
+            "--decimal" => use_decimal = true,
+            "--bit-shift" => { i += 1; bit_shift = argv[i].parse().expect("--bit-shift takes an integer"); }
+            "--bit-width" => {
+                i += 1;
+                bit_width = argv[i].parse().expect("--bit-width takes an integer");
+                assert!((1..=63).contains(&bit_width), "--bit-width must be in 1..=63");
+            }
+            "--bins" => { i += 1; bins = argv[i].parse().expect("--bins takes an integer"); }
+            "--load-factor" => {
+                i += 1;
+                load_factor = argv[i].parse().expect("--load-factor takes a float");
+                assert!(load_factor > 0.0 && load_factor <= 1.0, "--load-factor must be in (0, 1]");
+            }
+            "--arms" => {
+                i += 1;
+                let targets: Vec<f64> = argv[i].split(',').map(|p| p.parse().expect("--arms takes a comma-separated list of floats")).collect();
+                assert!(targets.iter().all(|&p| (0.0..=1.0).contains(&p)), "--arms proportions must each be in [0, 1]");
+                let sum: f64 = targets.iter().sum();
+                assert!((sum - 1.0).abs() < 1e-6, "--arms proportions must sum to 1.0 (got {})", sum);
+                arms = Some(targets);
+            }
+            "--compare" => {
+                i += 1;
+                let mut bounds = argv[i].split(',');
+                let lo = bounds.next().expect("--compare takes MIN,MAX").parse().expect("--compare bounds must be integers");
+                let hi = bounds.next().expect("--compare takes MIN,MAX").parse().expect("--compare bounds must be integers");
+                compare = Some((lo, hi));
+            }
+            "--mask" => { i += 1; mask = Some(argv[i].clone()); }
+            "--generator" => { i += 1; generator = argv[i].clone(); }
+            other => panic!("unrecognized argument: {}", other),
+        }
+        i += 1;
+    }
+
+    Args { use_decimal, bit_shift, bit_width, bins, load_factor, arms, compare, mask, generator }
+}
+
+/// Vose's alias method: builds an O(1)-per-draw sampler for a discrete
+/// distribution over study arms from arbitrary target proportions, so an SSN
+/// can be assigned to e.g. a 60/25/15 control/treatment-A/treatment-B split
+/// while staying fully reproducible from the SSN alone.
+struct AliasTable {
+    targets: Vec<f64>,
+    prob_of_val: Vec<f64>,
+    alias: Vec<usize>,
+}
+
+impl AliasTable {
+    fn build(targets: &[f64]) -> AliasTable {
+        let k = targets.len();
+        let mut prob_of_val = vec![0.0; k];
+        let mut alias = vec![0usize; k];
+        let mut scaled: Vec<f64> = targets.iter().map(|p| p * k as f64).collect();
+
+        let mut small: Vec<usize> = Vec::new();
+        let mut large: Vec<usize> = Vec::new();
+        for (i, &p) in scaled.iter().enumerate() {
+            if p < 1.0 {
+                small.push(i);
+            } else {
+                large.push(i);
+            }
+        }
+
+        while let (Some(s), Some(l)) = (small.pop(), large.pop()) {
+            prob_of_val[s] = scaled[s];
+            alias[s] = l;
+            scaled[l] -= 1.0 - scaled[s];
+            if scaled[l] < 1.0 {
+                small.push(l);
+            } else {
+                large.push(l);
+            }
+        }
+        // Whatever's left is a roundoff artifact of floating-point scaling
+        // that should be exactly 1.0; those cells always keep their own arm.
+        for i in large.into_iter().chain(small) {
+            prob_of_val[i] = 1.0;
+        }
+
+        AliasTable { targets: targets.to_vec(), prob_of_val, alias }
+    }
+
+    fn num_arms(&self) -> usize {
+        self.targets.len()
+    }
+}
+
+/// Deterministically assigns an SSN to one of `table`'s arms from
+/// `generator`'s output, the sole source of randomness: `index` picks the
+/// alias column and `coin` decides between that column and its alias.
+/// `coin` is rescaled by `generator.output_domain()` rather than a fixed
+/// constant, since generators don't share a common output range (Park-Miller
+/// stays under `TOTAL_OUTPUTS`, but `Pcg32`/`SeaStream` fill their full u32/
+/// u64 width).
+fn assign_arm_with(table: &AliasTable, generator: &dyn Generator, ssn: i64) -> usize {
+    let out = generator.output(ssn);
+    let k = table.num_arms() as u64;
+    let index = (out % k) as usize;
+    let coin = (out / k) as f64 / (generator.output_domain() / k as f64);
+    if coin <= table.prob_of_val[index] {
+        index
+    } else {
+        table.alias[index]
+    }
+}
+
+/// Shorthand for `assign_arm_with` against the default Park-Miller generator.
+fn assign_arm(table: &AliasTable, ssn: i64) -> usize {
+    assign_arm_with(table, &ParkMiller, ssn)
+}
+
+/// Confirms empirical arm counts over `min_ssn..max_ssn` match `table`'s
+/// target proportions within `tolerance` (e.g. 0.01 for +/-1 percentage
+/// point), printing the comparison and returning whether it held.
+fn verify_alias_assignment(table: &AliasTable, min_ssn: i64, max_ssn: i64, tolerance: f64) -> bool {
+    let mut counts = vec![0u64; table.num_arms()];
+    for ssn in min_ssn..max_ssn {
+        counts[assign_arm(table, ssn)] += 1;
+    }
+
+    let total = (max_ssn - min_ssn) as f64;
+    let mut ok = true;
+    for (arm, (&count, &target)) in counts.iter().zip(table.targets.iter()).enumerate() {
+        let empirical = count as f64 / total;
+        let diff = (empirical - target).abs();
+        println!("Arm {}: target {:.4}, empirical {:.4} ({} of {})", arm, target, empirical, count, total as u64);
+        if diff > tolerance {
+            ok = false;
+        }
+    }
+    ok
+}
+
+/// Which slice of the output bits is under test. Assignment code often keys
+/// off of only a few bits of a generator's output rather than the whole
+/// thing, so the quality of *that* slice is what actually matters.
+struct BitSlice {
+    shift: u32,
+    width: u32,
+}
+
+impl BitSlice {
+    fn extract(&self, val: u64) -> u64 {
+        (val >> self.shift) & ((1u64 << self.width) - 1)
+    }
+}
+
+/// Per-thread-partition accumulator for the chi-square uniformity test and
+/// the open-addressing clustering simulation.
+///
+/// Chi-square sees every item via `bin_counts`. The clustering table does
+/// not: slice values only take `2^bit_width` distinct values, so once more
+/// than `sample_limit` items have been fed in, every further insert starts
+/// from one of a small set of already-occupied slots and the linear probe
+/// degenerates into one long clump (quadratic instead of the O(1)-amortized
+/// case a real hash table load-factor test is meant to show). `sample_limit`
+/// caps insertions at `min(n, 2^bit_width)` so the table is never asked to
+/// hold more real entries than its domain actually has; `table` itself is a
+/// bitset (1 bit/slot, matching the collision-check `seen` array) rather
+/// than `Vec<bool>`, since the full run sizes this per thread.
+struct Metrics {
+    bin_counts: Vec<u64>,
+    table: Vec<u64>,
+    capacity: usize,
+    sample_limit: u64,
+    sampled: u64,
+    n: u64,
+}
+
+impl Metrics {
+    fn new(bins: usize, capacity: usize, sample_limit: u64) -> Self {
+        let capacity = capacity.max(1);
+        let words = capacity.div_ceil(64);
+        Metrics { bin_counts: vec![0; bins], table: vec![0u64; words], capacity, sample_limit, sampled: 0, n: 0 }
+    }
+
+    fn table_get(&self, idx: usize) -> bool {
+        self.table[idx / 64] & (1u64 << (idx % 64)) != 0
+    }
+
+    fn table_set(&mut self, idx: usize) {
+        self.table[idx / 64] |= 1u64 << (idx % 64);
+    }
+
+    fn record(&mut self, slice: &BitSlice, slice_val: u64) {
+        let domain = 1u64 << slice.width;
+        let num_bins = self.bin_counts.len();
+        let bin = ((slice_val as u128 * num_bins as u128) / domain as u128) as usize;
+        self.bin_counts[bin.min(num_bins - 1)] += 1;
+        self.n += 1;
+
+        if self.sampled >= self.sample_limit {
+            return;
+        }
+        let mut slot = (slice_val as usize) % self.capacity;
+        while self.table_get(slot) {
+            slot = (slot + 1) % self.capacity;
+        }
+        self.table_set(slot);
+        self.sampled += 1;
+    }
+
+    /// Average, over every occupied slot, of the length of the contiguous
+    /// run of occupied slots it belongs to (the standard linear-probing
+    /// cluster-length statistic; ~2.541 at load factor 0.5 for a good hash).
+    fn avg_cluster_length(&self) -> f64 {
+        if self.sampled == 0 {
+            return 0.0;
+        }
+        let capacity = self.capacity;
+        let start = match (0..capacity).find(|&i| !self.table_get(i)) {
+            Some(s) => s,
+            None => return capacity as f64,
+        };
+
+        let mut weighted_len: u64 = 0;
+        let mut total_occupied: u64 = 0;
+        let mut run_len: u64 = 0;
+        for offset in 0..=capacity {
+            let idx = (start + offset) % capacity;
+            if self.table_get(idx) {
+                run_len += 1;
+            } else if run_len > 0 {
+                weighted_len += run_len * run_len;
+                total_occupied += run_len;
+                run_len = 0;
+            }
+        }
+        weighted_len as f64 / total_occupied as f64
+    }
+}
+
+fn chi_square(bin_counts: &[u64]) -> f64 {
+    let total: u64 = bin_counts.iter().sum();
+    let expected = total as f64 / bin_counts.len() as f64;
+    bin_counts.iter().map(|&count| {
+        let diff = count as f64 - expected;
+        diff * diff / expected
+    }).sum()
+}
+
+/// Runs the collision/uniformity/clustering tests against every registered
+/// generator over `min_ssn..max_ssn` and prints a side-by-side table, so a
+/// team can justify its chosen deterministic hash with evidence instead of
+/// treating Park-Miller as the only option. This is single-threaded and
+/// uses a plain `HashSet` for collisions rather than the main run's
+/// domain-sized bitset, since the generators being compared aren't all
+/// bounded to `[0, LEHMER_M)` and the sub-range under comparison is
+/// expected to be much smaller than the full SSN space.
+fn run_comparison(generators: &[Box<dyn Generator>], min_ssn: i64, max_ssn: i64, slice: &BitSlice, bins: usize, load_factor: f64) {
+    let n = (max_ssn - min_ssn) as usize;
+    let domain = 1u64 << slice.width;
+    let sample_limit = (n as u64).min(domain);
+    let capacity = (sample_limit as f64 / load_factor).ceil() as usize;
+
+    println!("{:<12} {:>12} {:>12} {:>12} {:>12}", "generator", "n", "collisions", "chi-square", "avg-cluster");
+    for generator in generators {
+        let mut seen = std::collections::HashSet::with_capacity(n);
+        let mut metrics = Metrics::new(bins, capacity.max(1), sample_limit);
+        let mut collisions: u64 = 0;
+        for ssn in min_ssn..max_ssn {
+            let val = generator.output(ssn);
+            if !seen.insert(val) {
+                collisions += 1;
+            }
+            metrics.record(slice, slice.extract(val));
+        }
+        println!(
+            "{:<12} {:>12} {:>12} {:>12.2} {:>12.3}",
+            generator.name(), n, collisions, chi_square(&metrics.bin_counts), metrics.avg_cluster_length()
+        );
+    }
+}
+
+/// An iterator over every concrete 9-digit SSN matching a masked pattern
+/// like `123-45-####` or `#23-4#-6789`, where `#` marks an unknown digit.
+/// Dashes are cosmetic and accepted anywhere but don't count as a digit
+/// position. Built from the free-digit cartesian product, so memory use is
+/// O(number of wildcard digits), not O(number of matching SSNs).
+struct MaskedSsnIter {
+    digits: [Option<u8>; 9],
+    free_positions: Vec<usize>,
+    next_combo: u64,
+    total_combos: u64,
+}
+
+impl MaskedSsnIter {
+    fn new(pattern: &str) -> MaskedSsnIter {
+        let digit_chars: Vec<char> = pattern.chars().filter(|&c| c != '-').collect();
+        assert_eq!(digit_chars.len(), 9, "SSN pattern must have exactly 9 digit positions");
+
+        let mut digits = [None; 9];
+        let mut free_positions = Vec::new();
+        for (i, &c) in digit_chars.iter().enumerate() {
+            if c == '#' {
+                free_positions.push(i);
+            } else {
+                digits[i] = Some(c.to_digit(10).expect("SSN pattern digits must be 0-9 or '#'") as u8);
+            }
+        }
+
+        let total_combos = 10u64.pow(free_positions.len() as u32);
+        MaskedSsnIter { digits, free_positions, next_combo: 0, total_combos }
+    }
+}
+
+impl Iterator for MaskedSsnIter {
+    type Item = i64;
+
+    fn next(&mut self) -> Option<i64> {
+        if self.next_combo >= self.total_combos {
+            return None;
+        }
+
+        let mut digits = self.digits;
+        let mut remaining = self.next_combo;
+        for &pos in self.free_positions.iter().rev() {
+            digits[pos] = Some((remaining % 10) as u8);
+            remaining /= 10;
+        }
+
+        self.next_combo += 1;
+        Some(digits.iter().fold(0i64, |acc, d| acc * 10 + d.unwrap() as i64))
+    }
+}
+
+/// Runs every SSN matching `pattern` through `generator` (and, if `table` is
+/// given, through the alias assignment) and reports how the candidates
+/// spread across outputs and arms. Answers "given only a partial/redacted
+/// SSN, what's the range of arms this person could have been assigned to,
+/// and is any partial pattern pathologically skewed toward one arm?".
+fn report_masked_pattern(pattern: &str, generator: &dyn Generator, table: Option<&AliasTable>) {
+    let mut candidates: u64 = 0;
+    let mut min_output = u64::MAX;
+    let mut max_output = 0u64;
+    let mut arm_counts = table.map_or_else(Vec::new, |t| vec![0u64; t.num_arms()]);
+
+    for ssn in MaskedSsnIter::new(pattern) {
+        let output = generator.output(ssn);
+        min_output = min_output.min(output);
+        max_output = max_output.max(output);
+        if let Some(table) = table {
+            arm_counts[assign_arm_with(table, generator, ssn)] += 1;
+        }
+        candidates += 1;
+    }
+
+    println!("Pattern {} matches {} candidate SSNs", pattern, candidates);
+    println!("Output range: [{}, {}]", min_output, max_output);
+    if table.is_some() {
+        for (arm, &count) in arm_counts.iter().enumerate() {
+            println!("Arm {}: {} of {} candidates ({:.2}%)", arm, count, candidates, 100.0 * count as f64 / candidates as f64);
+        }
+    }
+}
+
 /// This function enumerates all possible social security numbers and runs them through
 /// the "hashing" algorithm. The question is whether we actually get an honest _shuffle_
-/// and not just a hash with collisions.
+/// and not just a hash with collisions, and, beyond that binary answer, how well
+/// distributed the SSN->value map actually is.
 ///
-/// There's probably a better way to do this, to be honest, but this is essentially
-/// a merge+heapsort that's doing everything in memory. It takes quite a bit of memory
-/// (like 6GB); and it takes quite a bit of time (probably because I'm
-/// pop-ing in the heaps instead of drain_sorted-ing, but that's an unstable feature
-/// and I don't want to install a nightly build).
+/// Collisions are tracked with a single shared bitset (one bit per possible
+/// output, ~256MB total) instead of sorting a billion values: each worker
+/// sets its output's bit with an atomic `fetch_or` and, if the bit was
+/// already set, bumps its own collision counter. No heaps, no merge, and no
+/// sort required to answer "have we seen this value before?".
 fn main() {
+    let args = parse_args();
+
     let num_threads: i64 = 10;
     let min_ssn: i64 = 1;
     let max_ssn: i64 = 1_000_000_000;
 
+    if let Some(pattern) = &args.mask {
+        let generators = all_generators();
+        let generator = generators.iter().find(|g| g.name() == args.generator)
+            .unwrap_or_else(|| panic!("unknown generator: {}", args.generator));
+        let table = args.arms.as_ref().map(|targets| AliasTable::build(targets));
+        report_masked_pattern(pattern, generator.as_ref(), table.as_ref());
+        return;
+    }
+
+    if let Some(targets) = &args.arms {
+        let table = AliasTable::build(targets);
+        let ok = verify_alias_assignment(&table, min_ssn, max_ssn, 0.001);
+        println!("Alias assignment {} target proportions within tolerance", if ok { "matches" } else { "does NOT match" });
+        return;
+    }
+
+    let slice = BitSlice { shift: args.bit_shift, width: args.bit_width };
+
+    if let Some((compare_min, compare_max)) = args.compare {
+        run_comparison(&all_generators(), compare_min, compare_max, &slice, args.bins, args.load_factor);
+        return;
+    }
+
+    let seen: Arc<Vec<AtomicU64>> = Arc::new((0..BITSET_WORDS).map(|_| AtomicU64::new(0)).collect());
+
     let mut handles = Vec::with_capacity(num_threads.try_into().unwrap());
 
     for num_thread in 0..num_threads {
@@ -42,82 +593,75 @@ fn main() {
         cq.rescale(10);
         let mut cr = Decimal::from_str("2836").unwrap();
         cr.rescale(10);
+        let seen = Arc::clone(&seen);
+        let use_decimal = args.use_decimal;
+        let bins = args.bins;
+        let load_factor = args.load_factor;
+        let slice = BitSlice { shift: slice.shift, width: slice.width };
 
         handles.push(
            thread::spawn(move || {
                 let min_val = pct_along(num_thread, num_threads, min_ssn, max_ssn);
                 let max_val = pct_along(num_thread + 1, num_threads, min_ssn, max_ssn);
-                // let mut hset = HashSet::new();
-                let mut heap = BinaryHeap::<u64>::new();
+                let domain = 1u64 << slice.width;
+                let sample_limit = ((max_val - min_val) as u64).min(domain);
+                let capacity = (sample_limit as f64 / load_factor).ceil() as usize;
+                let mut metrics = Metrics::new(bins, capacity.max(1), sample_limit);
+                let mut collisions: u64 = 0;
                 for i in min_val..max_val {
                     if i % 1_000_000 == 0 {
                         println!("On thread {} at iteration {} of {}", num_thread, i - min_val, max_val - min_val);
                     }
-                    let mut lsd = Decimal::from_i64(i).unwrap();
-                    lsd.rescale(10);
-
-                    let mut whi = lsd / cq;
-                    whi = whi.round_dp_with_strategy(0, RoundingStrategy::ToZero);
-                    whi.rescale(10);
-                    let mut wlo = lsd - cq * whi;
-                    wlo.rescale(10);
-                    lsd = ca * wlo - cr * whi;
-
-                    if lsd <= zero {
-                        lsd = lsd + cm;
-                    }
-                    let mut lrand = lsd / cm;
-                    lrand = lrand.round_dp_with_strategy(10, RoundingStrategy::ToZero);
-                    let mantissa = lrand.mantissa();
 
-                    // Assert that the result is actually between 0 and 1
-                    assert!(mantissa >= 0);
-                    assert!(lrand <= one);
+                    let val = if use_decimal {
+                        decimal_next(i, ca, cq, cr, cm, zero, one)
+                    } else {
+                        ParkMiller.output(i)
+                    };
+
+                    let word = (val / 64) as usize;
+                    let mask = 1u64 << (val % 64);
+                    let prev = seen[word].fetch_or(mask, Ordering::Relaxed);
+                    if prev & mask != 0 {
+                        collisions += 1;
+                    }
 
-                    // Then the result is only 10 digits, so won't _quite_ fit into
-                    // a u32. We'll keep it in a u64
-                    heap.push(mantissa as u64);
+                    metrics.record(&slice, slice.extract(val));
                 }
-                println!("On thread {} found length {}", num_thread, heap.len());
-                heap
+                let chi_sq = chi_square(&metrics.bin_counts);
+                let avg_cluster_length = metrics.avg_cluster_length();
+                println!(
+                    "On thread {} found {} collisions, chi-square {:.2}, avg cluster length {:.3}",
+                    num_thread, collisions, chi_sq, avg_cluster_length
+                );
+                (collisions, metrics.bin_counts, avg_cluster_length, metrics.n)
            })
         );
     }
 
-    // Join all the threads and gather the heaps
-    let mut heaps = Vec::with_capacity(num_threads.try_into().unwrap());
+    let mut num_identical_vals: u64 = 0;
+    let mut aggregate_bin_counts: Vec<u64> = vec![0; args.bins];
+    let mut weighted_cluster_length_sum: f64 = 0.0;
+    let mut total_n: u64 = 0;
     for handle in handles {
-        heaps.push(handle.join().unwrap());
-    }
-
-    let mut final_heap = BinaryHeap::<(u64, usize)>::new();
-    for i in 0..heaps.len() {
-        final_heap.push((heaps[i].pop().unwrap(), i));
-    }
-    let mut last_seen_val_pair = final_heap.pop().unwrap();
-    let mut last_seen_val = last_seen_val_pair.0;
-    let mut last_seen_val_pos = last_seen_val_pair.1;
-    if heaps[last_seen_val_pos].len() > 0 {
-        final_heap.push((heaps[last_seen_val_pos].pop().unwrap(), last_seen_val_pos));
-    }
-    let mut num_identical_vals = 0;
-    let mut counter = 1;
-    while final_heap.len() > 0 {
-        if counter % 10_000_000 == 0 {
-            println!("Made it through {} elts", counter);
-        }
-        last_seen_val_pair = final_heap.pop().unwrap();
-        if last_seen_val == last_seen_val_pair.0 {
-            num_identical_vals += 1;
-        }
-        counter += 1;
-
-        last_seen_val = last_seen_val_pair.0;
-        last_seen_val_pos = last_seen_val_pair.1;
-        if heaps[last_seen_val_pos].len() > 0 {
-            final_heap.push((heaps[last_seen_val_pos].pop().unwrap(), last_seen_val_pos));
+        let (collisions, bin_counts, avg_cluster_length, n) = handle.join().unwrap();
+        num_identical_vals += collisions;
+        for (agg, count) in aggregate_bin_counts.iter_mut().zip(bin_counts.iter()) {
+            *agg += count;
         }
+        weighted_cluster_length_sum += avg_cluster_length * n as f64;
+        total_n += n;
     }
 
-    println!("Have {} and {}", num_identical_vals, counter);
+    let num_hit: u64 = seen.iter().map(|word| word.load(Ordering::Relaxed).count_ones() as u64).sum();
+    let num_never_hit = TOTAL_OUTPUTS - num_hit;
+
+    println!("Have {} collisions and {} never-hit outputs (out of {})", num_identical_vals, num_never_hit, TOTAL_OUTPUTS);
+    println!(
+        "Aggregate: chi-square {:.2} over {} bins, avg cluster length {:.3} at load factor {}",
+        chi_square(&aggregate_bin_counts),
+        args.bins,
+        weighted_cluster_length_sum / total_n as f64,
+        args.load_factor
+    );
 }